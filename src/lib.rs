@@ -1,9 +1,9 @@
-use parking_lot::{Mutex, RwLock, RwLockUpgradableReadGuard};
+use parking_lot::{Mutex, RwLock};
 use rand::{Rng, rngs::OsRng};
 
 use rocket::{
     fairing::{self, Fairing, Info},
-    http::{Cookie, Status},
+    http::{Cookie, SameSite, Status},
     request::FromRequest,
     Outcome, Request, Response, Rocket, State,
 };
@@ -13,18 +13,291 @@ use std::collections::HashMap;
 use std::fmt::{self, Display, Formatter};
 use std::marker::PhantomData;
 use std::ops::Add;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-/// Session store (shared state)
+use time::Duration as CookieDuration;
+
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Pluggable storage for session data.
+///
+/// The default [`MemoryBackend`] keeps sessions in an in-process map, which
+/// is lost on restart and can't be shared between multiple app instances.
+/// Implement this trait to back sessions with Redis, a SQL database, or
+/// anything else, and install it with [`SessionFairing::with_backend`].
+///
+/// Backends with native expiry (e.g. Redis' `EXPIRE`) can treat
+/// `sweep_expired` as a no-op; `load` is still expected to return `None`
+/// for an expired entry.
+pub trait SessionBackend<D>: Send + Sync + 'static
+where
+    D: 'static + Sync + Send + Default,
+{
+    /// Load a session's data by ID. Returns `None` if the session doesn't
+    /// exist or has expired.
+    fn load(&self, id: &str) -> Option<D>;
+
+    /// Returns true if an entry for `id` is present, expired or not.
+    ///
+    /// Used to avoid handing out a session ID that's already taken. Note
+    /// there's a TOCTOU gap between a `contains` check and the `store` that
+    /// follows it (see `generate_unique_id`): two concurrent callers can
+    /// both observe a candidate ID as free and then both `store` under it,
+    /// with the later call winning. Given 16+ random characters this is
+    /// astronomically unlikely rather than impossible; backends aren't
+    /// required to close it.
+    fn contains(&self, id: &str) -> bool;
+
+    /// Insert or replace the stored data and expiry for `id`. See
+    /// `contains` for the TOCTOU caveat when used to claim a freshly
+    /// generated ID.
+    fn store(&self, id: &str, data: &D, expires: Instant);
+
+    /// Remove a session by ID.
+    fn remove(&self, id: &str);
+
+    /// Remove all expired sessions. Called periodically; backends with
+    /// native TTL support can leave this empty.
+    fn sweep_expired(&self);
+
+    /// Atomically read-modify-write the entry for `id`.
+    ///
+    /// `func` is called with `Some(data)` for a live (non-expired) entry, or
+    /// `None` otherwise, and returns `(new_entry, result)`: `Some((data,
+    /// expires))` to insert or update the entry, or `None` to remove it.
+    /// `result` is passed back to the caller unchanged.
+    ///
+    /// Implementations must serialize concurrent `mutate` calls for the
+    /// same `id` (e.g. a per-entry lock, or the backing store's native
+    /// transaction/CAS support): `Session::tap`, `try_tap`, and
+    /// `regenerate` rely on this to avoid lost updates and session-fixation
+    /// races that a plain `load` followed by a separate `store` can't
+    /// avoid, since another caller can run its own `load`/`store` pair in
+    /// between the two.
+    fn mutate<T>(&self, id: &str, func: impl FnOnce(Option<D>) -> (Option<(D, Instant)>, T)) -> T;
+}
+
+/// Data kept per session by [`MemoryBackend`].
 #[derive(Debug)]
-pub struct SessionStore<D>
+struct SessionInstance<D> {
+    /// Data object
+    data: D,
+    /// Expiry
+    expires: Instant,
+}
+
+/// The default, in-process [`SessionBackend`].
+///
+/// Sessions live only as long as the process and aren't shared between app
+/// instances; swap in a different backend if you need either.
+///
+/// Requires `D: Clone`: each read and write works on a clone of the stored
+/// data rather than handing out a reference into the map, so one session's
+/// `mutate` can't hold the lock open for as long as a caller keeps the data
+/// borrowed.
+#[derive(Debug)]
+pub struct MemoryBackend<D>
 where
     D: 'static + Sync + Send + Default,
 {
-    /// The internally mutable map of sessions
-    inner: RwLock<StoreInner<D>>,
-    // Session config
-    config: SessionConfig,
+    sessions: RwLock<HashMap<String, Mutex<SessionInstance<D>>>>,
+}
+
+impl<D> Default for MemoryBackend<D>
+where
+    D: 'static + Sync + Send + Default,
+{
+    fn default() -> Self {
+        Self {
+            sessions: Default::default(),
+        }
+    }
+}
+
+impl<D> SessionBackend<D> for MemoryBackend<D>
+where
+    D: 'static + Sync + Send + Default + Clone,
+{
+    fn load(&self, id: &str) -> Option<D> {
+        let rg = self.sessions.read();
+        let instance = rg.get(id)?.lock();
+        if instance.expires <= Instant::now() {
+            return None;
+        }
+        Some(instance.data.clone())
+    }
+
+    fn contains(&self, id: &str) -> bool {
+        self.sessions.read().contains_key(id)
+    }
+
+    fn store(&self, id: &str, data: &D, expires: Instant) {
+        let rg = self.sessions.read();
+        if let Some(instance) = rg.get(id) {
+            let mut instance = instance.lock();
+            instance.data = data.clone();
+            instance.expires = expires;
+            return;
+        }
+        drop(rg);
+
+        self.sessions.write().insert(
+            id.to_string(),
+            Mutex::new(SessionInstance {
+                data: data.clone(),
+                expires,
+            }),
+        );
+    }
+
+    fn remove(&self, id: &str) {
+        self.sessions.write().remove(id);
+    }
+
+    fn sweep_expired(&self) {
+        let now = Instant::now();
+        self.sessions
+            .write()
+            .retain(|_k, v| v.lock().expires > now);
+    }
+
+    fn mutate<T>(&self, id: &str, func: impl FnOnce(Option<D>) -> (Option<(D, Instant)>, T)) -> T {
+        // Fast path: the entry already exists. Holding only a *read* lock on
+        // the outer map while we lock the entry itself lets unrelated
+        // sessions keep making progress; the entry's own `Mutex` is what
+        // serializes this against any other `mutate`/`tap` on the same `id`.
+        let rg = self.sessions.read();
+        if let Some(entry) = rg.get(id) {
+            let mut instance = entry.lock();
+            let current = (instance.expires > Instant::now()).then(|| instance.data.clone());
+
+            let (new_value, result) = func(current);
+            match new_value {
+                Some((data, expires)) => {
+                    instance.data = data;
+                    instance.expires = expires;
+                }
+                None => {
+                    // Mark the entry expired before releasing its lock, and
+                    // only remove it if no other `mutate`/`store` has revived
+                    // it in the gap before the write lock is taken.
+                    instance.expires = Instant::now();
+                    drop(instance);
+                    drop(rg);
+                    let mut wg = self.sessions.write();
+                    if wg.get(id).map_or(false, |e| e.lock().expires <= Instant::now()) {
+                        wg.remove(id);
+                    }
+                    return result;
+                }
+            }
+            return result;
+        }
+        drop(rg);
+
+        // Slow path: no entry yet. Take the write lock for the whole
+        // check-then-insert so two concurrent `mutate` calls can't both
+        // decide to create the same brand-new `id`.
+        let mut wg = self.sessions.write();
+        if let Some(entry) = wg.get(id) {
+            let mut instance = entry.lock();
+            let current = (instance.expires > Instant::now()).then(|| instance.data.clone());
+
+            let (new_value, result) = func(current);
+            match new_value {
+                Some((data, expires)) => {
+                    instance.data = data;
+                    instance.expires = expires;
+                }
+                None => {
+                    drop(instance);
+                    wg.remove(id);
+                }
+            }
+            return result;
+        }
+
+        let (new_value, result) = func(None);
+        if let Some((data, expires)) = new_value {
+            wg.insert(id.to_string(), Mutex::new(SessionInstance { data, expires }));
+        }
+        result
+    }
+}
+
+/// A byte-oriented [`SessionBackend`] for stores that persist data as an
+/// opaque blob, e.g. Redis or a SQL `BLOB`/`TEXT` column.
+///
+/// Implement this instead of [`SessionBackend`] directly and get a
+/// [`SessionBackend<D>`] for any `D: Serialize + DeserializeOwned` for free
+/// (serialized with `serde_json`). Requires the `serde` feature.
+///
+/// The `SessionBackend::mutate` provided this way is a plain load followed
+/// by a store, not an atomic operation: concurrent `tap()` calls on the same
+/// session can overwrite each other's changes with such a backend.
+#[cfg(feature = "serde")]
+pub trait SerializedBackend: Send + Sync + 'static {
+    /// Load the raw bytes stored for `id`, if present and not expired.
+    fn load_bytes(&self, id: &str) -> Option<Vec<u8>>;
+
+    /// Returns true if an entry for `id` is present, expired or not.
+    fn contains(&self, id: &str) -> bool;
+
+    /// Insert or replace the raw bytes and expiry stored for `id`.
+    fn store_bytes(&self, id: &str, data: &[u8], expires: Instant);
+
+    /// Remove a session by ID.
+    fn remove(&self, id: &str);
+
+    /// Remove all expired sessions.
+    fn sweep_expired(&self);
+}
+
+#[cfg(feature = "serde")]
+impl<D, S> SessionBackend<D> for S
+where
+    S: SerializedBackend,
+    D: 'static + Sync + Send + Default + Serialize + DeserializeOwned,
+{
+    fn load(&self, id: &str) -> Option<D> {
+        let bytes = self.load_bytes(id)?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    fn contains(&self, id: &str) -> bool {
+        SerializedBackend::contains(self, id)
+    }
+
+    fn store(&self, id: &str, data: &D, expires: Instant) {
+        if let Ok(bytes) = serde_json::to_vec(data) {
+            self.store_bytes(id, &bytes, expires);
+        }
+    }
+
+    fn remove(&self, id: &str) {
+        SerializedBackend::remove(self, id)
+    }
+
+    fn sweep_expired(&self) {
+        SerializedBackend::sweep_expired(self)
+    }
+
+    fn mutate<T>(&self, id: &str, func: impl FnOnce(Option<D>) -> (Option<(D, Instant)>, T)) -> T {
+        // `SerializedBackend` exposes no locking primitive, so this is a
+        // best-effort, non-atomic load-then-store: two concurrent `mutate`
+        // calls for the same `id` can still race. Implement `SessionBackend`
+        // directly (e.g. using your store's native transaction or CAS
+        // support) if you need the stronger guarantee `mutate` promises.
+        let current = self.load(id);
+        let (new_value, result) = func(current);
+        match new_value {
+            Some((data, expires)) => SessionBackend::store(self, id, &data, expires),
+            None => SerializedBackend::remove(self, id),
+        }
+        result
+    }
 }
 
 /// Session config object
@@ -38,6 +311,12 @@ struct SessionConfig {
     cookie_path: Cow<'static, str>,
     /// Session ID character length
     cookie_len: usize,
+    /// Whether the session cookie is marked `HttpOnly`
+    cookie_http_only: bool,
+    /// Whether the session cookie is marked `Secure`
+    cookie_secure: bool,
+    /// `SameSite` attribute of the session cookie
+    cookie_same_site: SameSite,
 }
 
 impl Default for SessionConfig {
@@ -47,45 +326,44 @@ impl Default for SessionConfig {
             cookie_name: "rocket_session".into(),
             cookie_path: "/".into(),
             cookie_len: 16,
+            cookie_http_only: true,
+            cookie_secure: false,
+            cookie_same_site: SameSite::Lax,
         }
     }
 }
 
-/// Mutable object stored inside SessionStore behind a RwLock
+/// Session store (shared state)
 #[derive(Debug)]
-struct StoreInner<D>
+pub struct SessionStore<D, B = MemoryBackend<D>>
 where
     D: 'static + Sync + Send + Default,
+    B: SessionBackend<D>,
 {
-    sessions: HashMap<String, Mutex<SessionInstance<D>>>,
-    last_expiry_sweep: Instant,
+    /// The storage backend
+    backend: B,
+    /// Session config
+    config: SessionConfig,
+    /// Throttle for background-free expiry sweeps, see `new_session`
+    last_expiry_sweep: Mutex<Instant>,
+    phantom: PhantomData<D>,
 }
 
-impl<D> Default for StoreInner<D>
+impl<D, B> SessionStore<D, B>
 where
     D: 'static + Sync + Send + Default,
+    B: SessionBackend<D>,
 {
-    fn default() -> Self {
-        Self {
-            sessions: Default::default(),
-            // the first expiry sweep is scheduled one lifetime from start-up
-            last_expiry_sweep: Instant::now(),
-        }
+    /// Remove all expired sessions right now.
+    ///
+    /// Called on a timer by the background sweep task when one is
+    /// configured via `SessionFairing::with_sweep_interval`; safe to call
+    /// manually at any other time too.
+    pub fn remove_expired(&self) {
+        self.backend.sweep_expired();
     }
 }
 
-/// Session, as stored in the sessions store
-#[derive(Debug)]
-struct SessionInstance<D>
-where
-    D: 'static + Sync + Send + Default,
-{
-    /// Data object
-    data: D,
-    /// Expiry
-    expires: Instant,
-}
-
 /// Session ID newtype for rocket's "local_cache"
 #[derive(Clone, Debug)]
 struct SessionID(String);
@@ -102,6 +380,70 @@ impl Display for SessionID {
     }
 }
 
+/// Allocate a fresh session ID that isn't already taken.
+fn generate_unique_id<D, B>(store: &SessionStore<D, B>) -> SessionID
+where
+    D: 'static + Sync + Send + Default,
+    B: SessionBackend<D>,
+{
+    SessionID(loop {
+        let token: String = OsRng
+            .sample_iter(&rand::distributions::Alphanumeric)
+            .take(store.config.cookie_len)
+            .collect();
+
+        if !store.backend.contains(&token) {
+            break token;
+        }
+    })
+}
+
+/// Allocate a new, unique session ID and store a default-initialized
+/// session under it, sweeping expired sessions first if a full session
+/// lifespan has passed since the last such sweep.
+fn new_session<D, B>(store: &SessionStore<D, B>, expires: Instant) -> SessionID
+where
+    D: 'static + Sync + Send + Default,
+    B: SessionBackend<D>,
+{
+    {
+        let mut last_sweep = store.last_expiry_sweep.lock();
+        if last_sweep.elapsed() > store.config.lifespan {
+            store.backend.sweep_expired();
+            *last_sweep = Instant::now();
+        }
+    }
+
+    let new_id = generate_unique_id(store);
+    store.backend.store(new_id.as_str(), &D::default(), expires);
+
+    new_id
+}
+
+/// Spawn the background expiry-sweep task for `store`, woken every
+/// `interval`.
+///
+/// The task holds its own `Arc` clone of `store` and exits once that's the
+/// only one left (checked via `Arc::strong_count`), i.e. once whoever
+/// called this (normally `SessionFairing::on_attach`, via `rocket.manage`)
+/// has dropped their reference — so the task doesn't outlive the session
+/// store it's sweeping for.
+fn spawn_sweep_task<D, B>(store: Arc<SessionStore<D, B>>, interval: Duration)
+where
+    D: 'static + Sync + Send + Default,
+    B: SessionBackend<D>,
+{
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+
+        if Arc::strong_count(&store) <= 1 {
+            break;
+        }
+
+        store.remove_expired();
+    });
+}
+
 /// Session instance
 ///
 /// To access the active session, simply add it as an argument to a route function.
@@ -109,111 +451,63 @@ impl Display for SessionID {
 /// Sessions are started, restored, or expired in the `FromRequest::from_request()` method
 /// when a `Session` is prepared for one of the route functions.
 #[derive(Debug)]
-pub struct Session<'a, D>
+pub struct Session<'a, D, B = MemoryBackend<D>>
 where
     D: 'static + Sync + Send + Default,
+    B: SessionBackend<D>,
 {
     /// The shared state reference
-    store: State<'a, SessionStore<D>>,
-    /// Session ID
-    id: &'a SessionID,
+    store: State<'a, Arc<SessionStore<D, B>>>,
+    /// Session ID, behind a lock so `regenerate()` can rotate it in place
+    /// and have `SessionFairing::on_response` pick up the new value.
+    id: &'a Mutex<SessionID>,
 }
 
-impl<'a, 'r, D> FromRequest<'a, 'r> for Session<'a, D>
+impl<'a, 'r, D, B> FromRequest<'a, 'r> for Session<'a, D, B>
 where
     D: 'static + Sync + Send + Default,
+    B: SessionBackend<D>,
 {
     type Error = ();
 
     fn from_request(request: &'a Request<'r>) -> Outcome<Self, (Status, Self::Error), ()> {
-        let store: State<SessionStore<D>> = request.guard().unwrap();
+        let store: State<Arc<SessionStore<D, B>>> = request.guard().unwrap();
         Outcome::Success(Session {
             id: request.local_cache(|| {
-                let store_ug = store.inner.upgradable_read();
-
-                // Resolve session ID
-                let id = if let Some(cookie) = request.cookies().get(&store.config.cookie_name) {
-                    Some(SessionID(cookie.value().to_string()))
-                } else {
-                    None
-                };
+                let cookie_id = request
+                    .cookies()
+                    .get(&store.config.cookie_name)
+                    .map(|cookie| SessionID(cookie.value().to_string()));
 
                 let expires = Instant::now().add(store.config.lifespan);
 
-                if let Some(m) = id
-                    .as_ref()
-                    .and_then(|token| store_ug.sessions.get(token.as_str()))
-                {
-                    // --- ID obtained from a cookie && session found in the store ---
-
-                    let mut inner = m.lock();
-                    if inner.expires <= Instant::now() {
-                        // Session expired, reuse the ID but drop data.
-                        inner.data = D::default();
-                    }
-
-                    // Session is extended by making a request with valid ID
-                    inner.expires = expires;
-
-                    id.unwrap()
-                } else {
-                    // --- ID missing or session not found ---
-
-                    // Get exclusive write access to the map
-                    let mut store_wg = RwLockUpgradableReadGuard::upgrade(store_ug);
-
-                    // This branch runs less often, and we already have write access,
-                    // let's check if any sessions expired. We don't want to hog memory
-                    // forever by abandoned sessions (e.g. when a client lost their cookie)
-
-                    // Throttle by lifespan - e.g. sweep every hour
-                    if store_wg.last_expiry_sweep.elapsed() > store.config.lifespan {
-                        let now = Instant::now();
-                        store_wg.sessions.retain(|_k, v| v.lock().expires > now);
-
-                        store_wg.last_expiry_sweep = now;
+                let id = match cookie_id {
+                    // ID obtained from a cookie && an entry for it exists (possibly expired)
+                    Some(id) if store.backend.contains(id.as_str()) => {
+                        // Session is extended by making a request with a valid ID.
+                        // Go through `mutate` (not a separate load+store) so this
+                        // doesn't race with a concurrent `tap()` on the same ID.
+                        store.backend.mutate(id.as_str(), |current| {
+                            (Some((current.unwrap_or_default(), expires)), ())
+                        });
+                        id
                     }
+                    // ID missing, or not found in the backend
+                    _ => new_session(&store, expires),
+                };
 
-                    // Find a new unique ID - we are still safely inside the write guard
-                    let new_id = SessionID(loop {
-                        let token: String = OsRng
-                            .sample_iter(&rand::distributions::Alphanumeric)
-                            .take(store.config.cookie_len)
-                            .collect();
-
-                        if !store_wg.sessions.contains_key(&token) {
-                            break token;
-                        }
-                    });
-
-                    store_wg.sessions.insert(
-                        new_id.to_string(),
-                        Mutex::new(SessionInstance {
-                            data: Default::default(),
-                            expires,
-                        }),
-                    );
-
-                    new_id
-                }
+                Mutex::new(id)
             }),
             store,
         })
     }
 }
 
-impl<'a, D> Session<'a, D>
+impl<'a, D, B> Session<'a, D, B>
 where
     D: 'static + Sync + Send + Default,
+    B: SessionBackend<D>,
 {
-    /// Create the session fairing.
-    ///
-    /// You can configure the session store by calling chained methods on the returned value
-    /// before passing it to `rocket.attach()`
-    pub fn fairing() -> SessionFairing<D> {
-        SessionFairing::<D>::new()
-    }
-
     /// Clear session data (replace the value with default)
     pub fn clear(&self) {
         self.tap(|m| {
@@ -224,41 +518,174 @@ where
     /// Access the session's data using a closure.
     ///
     /// The closure is called with the data value as a mutable argument,
-    /// and can return any value to be is passed up to the caller.
+    /// and can return any value to be is passed up to the caller. The read,
+    /// mutation, and write-back happen in one `SessionBackend::mutate` call,
+    /// so with a backend that serializes `mutate` (such as `MemoryBackend`)
+    /// two concurrent `tap()` calls on the same session (e.g. from two
+    /// browser tabs) can't clobber each other.
     pub fn tap<T>(&self, func: impl FnOnce(&mut D) -> T) -> T {
-        // Use a read guard, so other already active sessions are not blocked
-        // from accessing the store. New incoming clients may be blocked until
-        // the tap() call finishes
-        let store_rg = self.store.inner.read();
+        let id = self.id.lock().clone();
+        let expires = Instant::now().add(self.store.config.lifespan);
+
+        self.store.backend.mutate(id.as_str(), |current| {
+            let mut data = current.unwrap_or_default();
+            let result = func(&mut data);
+            (Some((data, expires)), result)
+        })
+    }
 
-        // Unlock the session's mutex.
-        // Expiry was checked and prolonged at the beginning of the request
-        let mut instance = store_rg
-            .sessions
-            .get(self.id.as_str())
-            .expect("Session data unexpectedly missing")
-            .lock();
+    /// Access the session's data read-only, without requiring `&mut`.
+    ///
+    /// Like `tap`, a missing or expired session is presented as a
+    /// default-initialized value; unlike `tap`, nothing is written back.
+    pub fn tap_ref<T>(&self, func: impl FnOnce(&D) -> T) -> T {
+        let id = self.id.lock().clone();
+        let data = self.store.backend.load(id.as_str()).unwrap_or_default();
 
-        func(&mut instance.data)
+        func(&data)
+    }
+
+    /// Like `tap`, but returns `None` instead of silently operating on a
+    /// fresh default value if the session was swept between the start of
+    /// the request and this call. Atomic in the same sense as `tap`.
+    pub fn try_tap<T>(&self, func: impl FnOnce(&mut D) -> T) -> Option<T> {
+        let id = self.id.lock().clone();
+        let expires = Instant::now().add(self.store.config.lifespan);
+
+        self.store.backend.mutate(id.as_str(), |current| {
+            let mut data = match current {
+                Some(data) => data,
+                None => return (None, None),
+            };
+            let result = func(&mut data);
+            (Some((data, expires)), Some(result))
+        })
+    }
+
+    /// Replace this session's ID with a freshly generated one, moving its
+    /// data across and dropping the old entry.
+    ///
+    /// Call this right after a privilege change (e.g. login) to prevent
+    /// session fixation: an attacker who planted a session ID in the
+    /// victim's cookie before authentication is left holding a dead ID.
+    ///
+    /// The old entry is read and removed as one atomic operation (via
+    /// `SessionBackend::mutate`), so a request racing in on the old cookie
+    /// can't land a `store()` for the old ID in between our read and our
+    /// remove: it either completes entirely before `regenerate()` starts
+    /// (and its changes are the ones carried across), or lands after the
+    /// old ID is already gone (and it just starts a fresh, unprivileged
+    /// session under that ID) — never a resurrection of the old ID holding
+    /// the newly-privileged data.
+    pub fn regenerate(&self) {
+        let mut id_guard = self.id.lock();
+        regenerate_id(&self.store, &mut id_guard);
+    }
+}
+
+/// Move `id`'s data to a freshly generated ID and remove the old entry,
+/// updating `id` in place. See `Session::regenerate` for why this is
+/// written as a single atomic `mutate` on the old ID rather than a
+/// `load`/`store`/`remove` sequence.
+fn regenerate_id<D, B>(store: &SessionStore<D, B>, id: &mut SessionID)
+where
+    D: 'static + Sync + Send + Default,
+    B: SessionBackend<D>,
+{
+    let expires = Instant::now().add(store.config.lifespan);
+    let new_id = generate_unique_id(store);
+
+    let data = store
+        .backend
+        .mutate(id.as_str(), |current| (None, current.unwrap_or_default()));
+
+    store.backend.store(new_id.as_str(), &data, expires);
+
+    *id = new_id;
+}
+
+impl<'a, D> Session<'a, D, MemoryBackend<D>>
+where
+    D: 'static + Sync + Send + Default + Clone,
+{
+    /// Create the session fairing.
+    ///
+    /// You can configure the session store by calling chained methods on the returned value
+    /// before passing it to `rocket.attach()`
+    ///
+    /// Note this requires `D: Clone` (on top of `Default`), because the
+    /// default [`MemoryBackend`] does. Call `SessionFairing::with_backend`
+    /// on the returned fairing to bring your own [`SessionBackend`]; since
+    /// this function is the only way to obtain a fairing, `D: Clone` is
+    /// required even then.
+    pub fn fairing() -> SessionFairing<D, MemoryBackend<D>> {
+        SessionFairing::<D, MemoryBackend<D>>::new()
     }
 }
 
 /// Fairing struct
-#[derive(Default)]
-pub struct SessionFairing<D>
+pub struct SessionFairing<D, B = MemoryBackend<D>>
 where
     D: 'static + Sync + Send + Default,
+    B: SessionBackend<D>,
 {
     config: SessionConfig,
+    /// Interval for the background expiry-sweep task; `None` (the default)
+    /// means sessions are only swept opportunistically, piggy-backed on
+    /// incoming requests (see `new_session`).
+    sweep_interval: Option<Duration>,
+    // `Fairing::on_attach` only gets `&self`, so the (possibly non-`Clone`)
+    // backend is taken out of here once, at attach time.
+    backend: Mutex<Option<B>>,
     phantom: PhantomData<D>,
 }
 
-impl<D> SessionFairing<D>
+impl<D, B> SessionFairing<D, B>
 where
     D: 'static + Sync + Send + Default,
+    B: SessionBackend<D> + Default,
 {
     fn new() -> Self {
-        Self::default()
+        Self {
+            config: Default::default(),
+            sweep_interval: None,
+            backend: Mutex::new(Some(B::default())),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<D, B> SessionFairing<D, B>
+where
+    D: 'static + Sync + Send + Default,
+    B: SessionBackend<D>,
+{
+    /// Use a custom [`SessionBackend`] instead of the default in-memory one,
+    /// e.g. one backed by Redis or a SQL database.
+    pub fn with_backend<B2>(self, backend: B2) -> SessionFairing<D, B2>
+    where
+        B2: SessionBackend<D>,
+    {
+        SessionFairing {
+            config: self.config,
+            sweep_interval: self.sweep_interval,
+            backend: Mutex::new(Some(backend)),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Run a background task that sweeps expired sessions every `interval`,
+    /// instead of relying solely on the opportunistic sweep piggy-backed on
+    /// incoming requests.
+    ///
+    /// The task exits on its own once the attached `Rocket`'s managed state
+    /// is dropped (e.g. at the end of a test), so it doesn't leak a thread
+    /// per attachment.
+    ///
+    /// Call on the fairing before passing it to `rocket.attach()`
+    pub fn with_sweep_interval(mut self, interval: Duration) -> Self {
+        self.sweep_interval = Some(interval);
+        self
     }
 
     /// Set session lifetime (expiration time).
@@ -292,11 +719,37 @@ where
         self.config.cookie_path = path.into();
         self
     }
+
+    /// Set whether the session cookie is marked `HttpOnly` (defaults to `true`).
+    ///
+    /// Call on the fairing before passing it to `rocket.attach()`
+    pub fn with_http_only(mut self, http_only: bool) -> Self {
+        self.config.cookie_http_only = http_only;
+        self
+    }
+
+    /// Set whether the session cookie is marked `Secure`, i.e. only sent over HTTPS
+    /// (defaults to `false`, since not every deployment terminates TLS at the app).
+    ///
+    /// Call on the fairing before passing it to `rocket.attach()`
+    pub fn with_secure(mut self, secure: bool) -> Self {
+        self.config.cookie_secure = secure;
+        self
+    }
+
+    /// Set the `SameSite` attribute of the session cookie (defaults to `Lax`).
+    ///
+    /// Call on the fairing before passing it to `rocket.attach()`
+    pub fn with_same_site(mut self, same_site: SameSite) -> Self {
+        self.config.cookie_same_site = same_site;
+        self
+    }
 }
 
-impl<D> Fairing for SessionFairing<D>
+impl<D, B> Fairing for SessionFairing<D, B>
 where
     D: 'static + Sync + Send + Default,
+    B: SessionBackend<D>,
 {
     fn info(&self) -> Info {
         Info {
@@ -306,23 +759,163 @@ where
     }
 
     fn on_attach(&self, rocket: Rocket) -> Result<Rocket, Rocket> {
-        // install the store singleton
-        Ok(rocket.manage(SessionStore::<D> {
-            inner: Default::default(),
+        let backend = self
+            .backend
+            .lock()
+            .take()
+            .expect("SessionFairing attached more than once");
+
+        let store = Arc::new(SessionStore::<D, B> {
+            backend,
             config: self.config.clone(),
-        }))
+            last_expiry_sweep: Mutex::new(Instant::now()),
+            phantom: PhantomData,
+        });
+
+        if let Some(interval) = self.sweep_interval {
+            spawn_sweep_task(Arc::clone(&store), interval);
+        }
+
+        // install the store singleton
+        Ok(rocket.manage(store))
     }
 
     fn on_response<'r>(&self, request: &'r Request, response: &mut Response) {
-        // send the session cookie, if session started
-        let session = request.local_cache(|| SessionID("".to_string()));
+        // send the session cookie, if session started (or rotated by `regenerate()`)
+        let session = request.local_cache(|| Mutex::new(SessionID("".to_string())));
+        let session_id = session.lock().to_string();
+
+        if !session_id.is_empty() {
+            // `Duration::as_secs` truncates; add one second so the cookie doesn't
+            // expire fractionally before the session actually does. Clamp to
+            // `i64::MAX` instead of letting an extreme configured lifespan
+            // wrap negative through the `u64 -> i64` cast.
+            let max_age_secs = self.config.lifespan.as_secs().min(i64::MAX as u64 - 1) as i64 + 1;
+            let max_age = CookieDuration::seconds(max_age_secs);
 
-        if !session.0.is_empty() {
             response.adjoin_header(
-                Cookie::build(self.config.cookie_name.clone(), session.to_string())
-                    .path("/")
+                Cookie::build(self.config.cookie_name.clone(), session_id)
+                    .path(self.config.cookie_path.clone())
+                    .http_only(self.config.cookie_http_only)
+                    .secure(self.config.cookie_secure)
+                    .same_site(self.config.cookie_same_site)
+                    .max_age(max_age)
+                    .expires(time::now() + max_age)
                     .finish(),
             );
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[derive(Default, Clone)]
+    struct Counter {
+        n: u32,
+    }
+
+    fn test_store() -> SessionStore<Counter, MemoryBackend<Counter>> {
+        SessionStore {
+            backend: MemoryBackend::default(),
+            config: SessionConfig::default(),
+            last_expiry_sweep: Mutex::new(Instant::now()),
+            phantom: PhantomData,
+        }
+    }
+
+    /// `Session::tap` is `SessionBackend::mutate` plus ID/expiry bookkeeping
+    /// around it; constructing a real `Session` needs a live `rocket::Request`
+    /// from an actual HTTP round trip, which a unit test can't produce. Drive
+    /// `MemoryBackend::mutate` directly instead, with N threads racing to
+    /// increment the same counter the way concurrent `tap()` calls would, to
+    /// catch a regression in its fast/slow-path locking (see `f84677f`, which
+    /// fixed exactly this kind of lost update).
+    #[test]
+    fn mutate_is_atomic_across_concurrent_callers() {
+        use std::sync::Barrier;
+
+        let backend = Arc::new(MemoryBackend::<Counter>::default());
+        let expires = Instant::now() + Duration::from_secs(60);
+        backend.store("sess", &Counter::default(), expires);
+
+        // A barrier lines every thread up to start hammering the same entry
+        // at once, and each does many increments rather than one, so a
+        // load/store-split regression gets many chances to lose an update
+        // instead of relying on hitting one vanishingly small race window.
+        const THREADS: usize = 16;
+        const ITERS_PER_THREAD: usize = 200;
+        let barrier = Arc::new(Barrier::new(THREADS));
+
+        let handles: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let backend = Arc::clone(&backend);
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    barrier.wait();
+                    for _ in 0..ITERS_PER_THREAD {
+                        backend.mutate("sess", |current| {
+                            let mut data = current.unwrap_or_default();
+                            data.n += 1;
+                            (Some((data, expires)), ())
+                        });
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(backend.load("sess").unwrap().n, (THREADS * ITERS_PER_THREAD) as u32);
+    }
+
+    /// Exercises the same sequence `Session::regenerate` runs (see
+    /// `4b95dbb`): the old ID's data should show up under the new ID, and the
+    /// old ID should be gone.
+    #[test]
+    fn regenerate_moves_data_and_removes_old_id() {
+        let store = test_store();
+        let old_id = new_session(&store, Instant::now() + store.config.lifespan);
+
+        store.backend.mutate(old_id.as_str(), |current| {
+            let mut data = current.unwrap_or_default();
+            data.n = 42;
+            (Some((data, Instant::now() + store.config.lifespan)), ())
+        });
+
+        let mut id = old_id.clone();
+        regenerate_id(&store, &mut id);
+
+        assert_ne!(id.as_str(), old_id.as_str());
+        assert!(!store.backend.contains(old_id.as_str()));
+        assert_eq!(store.backend.load(id.as_str()).unwrap().n, 42);
+    }
+
+    /// See `a1463c4`'s follow-up fix: the sweep task used to loop forever
+    /// with no way to notice the store it was sweeping for had become
+    /// unreachable. It should now drop its own `Arc` clone and exit once
+    /// nothing else references the store.
+    #[test]
+    fn sweep_task_exits_once_store_is_dropped() {
+        let store = Arc::new(test_store());
+        spawn_sweep_task(Arc::clone(&store), Duration::from_millis(5));
+
+        let weak = Arc::downgrade(&store);
+        drop(store);
+
+        let mut tries = 0;
+        while weak.upgrade().is_some() && tries < 50 {
+            thread::sleep(Duration::from_millis(10));
+            tries += 1;
+        }
+
+        assert!(
+            weak.upgrade().is_none(),
+            "sweep task should exit and drop its Arc once the store has no other owners"
+        );
+    }
+}